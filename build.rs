@@ -18,20 +18,50 @@ fn sdk_include_path_for(sdk: &str) -> String {
     inc_path.to_str().expect("invalid include path").to_string()
 }
 
+/// Whether `target` names an Apple simulator rather than a physical device.
+///
+/// Simulator triples either carry the explicit `-sim`/`-simulator` suffix
+/// (e.g. `aarch64-apple-ios-sim`) or are the legacy `x86_64` host-arch form.
+/// Mac Catalyst (`-macabi`) runs on the host and is never a simulator, even
+/// though its `x86_64` variant would otherwise match the host-arch rule.
+fn is_apple_simulator(target: &str) -> bool {
+    if target.contains("-macabi") {
+        return false;
+    }
+    target.contains("-sim") || target.starts_with("x86_64")
+}
+
+/// Resolve an Apple `CARGO_CFG_TARGET_OS` to its `xcrun --sdk` name and the
+/// `clang`/bindgen `--target=` triple that selects the matching headers.
+///
+/// macOS has no simulator; every other platform maps to a device/simulator
+/// pair. The returned triple is only meaningful for `aarch64` builds, where
+/// bindgen needs it spelled out (see rust-bindgen#1211).
+fn apple_sdk(os: &str, target: &str) -> Option<(&'static str, &'static str)> {
+    // Mac Catalyst (the `ios`-`macabi` ABI) links against the macOS SDK
+    // regardless of the nominal target OS or arch.
+    if target.contains("-macabi") {
+        return Some(("macosx", "arm64-apple-ios-macabi"));
+    }
+    let sim = is_apple_simulator(target);
+    match os {
+        "macos" => Some(("macosx", "arm64-apple-macos")),
+        "ios" if sim => Some(("iphonesimulator", "arm64-apple-ios-simulator")),
+        "ios" => Some(("iphoneos", "arm64-apple-ios")),
+        "tvos" if sim => Some(("appletvsimulator", "arm64-apple-tvos-simulator")),
+        "tvos" => Some(("appletvos", "arm64-apple-tvos")),
+        "watchos" if sim => Some(("watchsimulator", "arm64-apple-watchos-simulator")),
+        "watchos" => Some(("watchos", "arm64-apple-watchos")),
+        "visionos" if sim => Some(("xrsimulator", "arm64-apple-xros-simulator")),
+        "visionos" => Some(("xros", "arm64-apple-xros")),
+        _ => None,
+    }
+}
+
 fn sdk_include_path() -> Option<String> {
     let os = env::var("CARGO_CFG_TARGET_OS").unwrap();
     let target = env::var("TARGET").unwrap();
-    match os.as_str() {
-        "ios" => {
-            if target == "x86_64-apple-ios" || target == "aarch64-apple-ios-sim" {
-                Some(sdk_include_path_for("iphonesimulator"))
-            } else {
-                Some(sdk_include_path_for("iphoneos"))
-            }
-        }
-        "macos" => Some(sdk_include_path_for("macosx")),
-        _ => None,
-    }
+    apple_sdk(&os, &target).map(|(sdk, _)| sdk_include_path_for(sdk))
 }
 
 fn android_sysroot() -> Option<PathBuf> {
@@ -66,6 +96,88 @@ fn android_api() -> String {
     env::var("ANDROID_API").unwrap_or_else(|_| "21".into())
 }
 
+/// Whether we are building for a Windows target (MSVC or MinGW).
+fn target_is_windows() -> bool {
+    env::var("CARGO_CFG_TARGET_OS").map(|os| os == "windows").unwrap_or(false)
+}
+
+/// Whether the named cargo feature is active for this build.
+fn feature(name: &str) -> bool {
+    let var = format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"));
+    env::var(var).is_ok()
+}
+
+/// Whether IPv6 is compiled in. On to match lwIP's (and the baseline's)
+/// default; memory-constrained targets opt out with the `no-ipv6` feature.
+fn ipv6_enabled() -> bool {
+    !feature("no-ipv6")
+}
+
+/// lwipopts `#define`s that must reach both `cc` and bindgen so the generated
+/// constants and the compiled library agree. Derived from the active features.
+fn lwip_defines() -> Vec<(&'static str, Option<&'static str>)> {
+    let mut defs = Vec::new();
+    if feature("stats") {
+        // Opt-in counters used by `NetStack::stats()`.
+        defs.push(("LWIP_STATS", Some("1")));
+        defs.push(("MEM_STATS", Some("1")));
+        defs.push(("MEMP_STATS", Some("1")));
+        defs.push(("TCP_STATS", Some("1")));
+        defs.push(("UDP_STATS", Some("1")));
+        defs.push(("IP_STATS", Some("1")));
+        defs.push(("ICMP_STATS", Some("1")));
+        defs.push(("LINK_STATS", Some("1")));
+    }
+    if feature("lwip-debug") {
+        // Route lwIP's printf-style tracing through the embedder's callback.
+        defs.push(("LWIP_DEBUG", None));
+        defs.push(("TCP_DEBUG", Some("LWIP_DBG_ON")));
+        defs.push(("UDP_DEBUG", Some("LWIP_DBG_ON")));
+        defs.push(("IP_DEBUG", Some("LWIP_DBG_ON")));
+        defs.push(("MEMP_DEBUG", Some("LWIP_DBG_ON")));
+    }
+    // Multicast membership: `igmp.c`/`mld6.c` wrap their whole body in these
+    // guards (both default 0 in opt.h), so they must be defined or the files
+    // compile to nothing and `join_multicast_*` has no effect.
+    defs.push(("LWIP_IGMP", Some("1")));
+    // MLDv6 only makes sense when IPv6 (and mld6.c/ip6.c) are compiled in.
+    if ipv6_enabled() {
+        defs.push(("LWIP_IPV6_MLD", Some("1")));
+    }
+    defs.push(("LWIP_IPV6", Some(if ipv6_enabled() { "1" } else { "0" })));
+    if feature("tcp-window") {
+        // `TCP_WND`/`TCP_SND_BUF` are leaked to a `&'static str` so they can
+        // share the `lwip_defines()` vec shape; the env values are build inputs.
+        if let Some(v) = numeric_env("LWIP_TCP_WND") {
+            defs.push(("TCP_WND", Some(v)));
+        }
+        if let Some(v) = numeric_env("LWIP_TCP_SND_BUF") {
+            defs.push(("TCP_SND_BUF", Some(v)));
+        }
+    }
+    if feature("mem-size") {
+        if let Some(v) = numeric_env("LWIP_MEM_SIZE") {
+            defs.push(("MEM_SIZE", Some(v)));
+        }
+    }
+    if feature("udp-bind-netif") {
+        defs.push(("HAVE_LWIP_UDP_BIND_NETIF", Some("1")));
+    }
+    defs
+}
+
+/// Read a build-time numeric knob from the environment, validating that it is
+/// an integer before it is pasted into a `#define`. Leaked to `'static` so it
+/// can live alongside the literal defines.
+fn numeric_env(var: &str) -> Option<&'static str> {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let raw = env::var(var).ok()?;
+    raw.trim()
+        .parse::<u32>()
+        .unwrap_or_else(|_| panic!("{} must be an unsigned integer, got {:?}", var, raw));
+    Some(Box::leak(raw.trim().to_string().into_boxed_str()))
+}
+
 fn compile_lwip() {
     let mut build = cc::Build::new();
     build
@@ -90,24 +202,70 @@ fn compile_lwip() {
         // .file("src/lwip/core/ipv4/dhcp.c")
         // .file("src/lwip/core/ipv4/etharp.c")
         .file("src/lwip/core/ipv4/icmp.c")
-        // .file("src/lwip/core/ipv4/igmp.c")
+        .file("src/lwip/core/ipv4/igmp.c")
         .file("src/lwip/core/ipv4/ip4_frag.c")
         .file("src/lwip/core/ipv4/ip4.c")
         .file("src/lwip/core/ipv4/ip4_addr.c")
-        // .file("src/lwip/core/ipv6/dhcp6.c")
-        // .file("src/lwip/core/ipv6/ethip6.c")
-        .file("src/lwip/core/ipv6/icmp6.c")
-        // .file("src/lwip/core/ipv6/inet6.c")
-        .file("src/lwip/core/ipv6/ip6.c")
-        .file("src/lwip/core/ipv6/ip6_addr.c")
-        .file("src/lwip/core/ipv6/ip6_frag.c")
-        // .file("src/lwip/core/ipv6/mld6.c")
-        .file("src/lwip/core/ipv6/nd6.c")
-        .file("src/lwip/custom/sys_arch.c")
         .include("src/lwip/custom")
         .include("src/lwip/include")
         .warnings(false)
         .flag_if_supported("-Wno-everything");
+
+    // lwIP's `sys_arch` shims are threading-model specific: the default port
+    // targets POSIX, so Windows needs a Win32 implementation instead.
+    if target_is_windows() {
+        build.file("src/lwip/custom/sys_arch_win.c");
+        // lwIP ships Win32 compatibility shims that must be selected at compile
+        // time; without these it assumes a POSIX `errno`/`timeval`/socket layer.
+        build
+            .define("WIN32", None)
+            .define("_WINSOCK_DEPRECATED_NO_WARNINGS", None)
+            .define("LWIP_TIMEVAL_PRIVATE", "0")
+            .define("LWIP_ERRNO_STDINCLUDE", None);
+    } else {
+        build.file("src/lwip/custom/sys_arch.c");
+    }
+
+    // Helper that reads `ip_current_dest_addr()`/`ip_current_dest_port()` for
+    // the udp recv trampoline (those accessors are macros, not callable via FFI).
+    build.file("src/lwip/custom/udp_dest.c");
+
+    // IPv6 is on by default; memory-constrained targets can drop it (and its
+    // source files) by enabling the `no-ipv6` feature.
+    if ipv6_enabled() {
+        build
+            // .file("src/lwip/core/ipv6/dhcp6.c")
+            // .file("src/lwip/core/ipv6/ethip6.c")
+            .file("src/lwip/core/ipv6/icmp6.c")
+            // .file("src/lwip/core/ipv6/inet6.c")
+            .file("src/lwip/core/ipv6/ip6.c")
+            .file("src/lwip/core/ipv6/ip6_addr.c")
+            .file("src/lwip/core/ipv6/ip6_frag.c")
+            .file("src/lwip/core/ipv6/mld6.c")
+            .file("src/lwip/core/ipv6/nd6.c");
+    }
+
+    if feature("stats") {
+        build.file("src/lwip/core/stats.c");
+    }
+
+    if feature("lwip-debug") {
+        // Route lwIP's diagnostics/assertions into the custom sink, which in
+        // turn forwards them to an embedder-supplied callback (see debug.rs).
+        build.file("src/lwip/custom/debug_sink.c");
+        build.define(
+            "LWIP_PLATFORM_DIAG(x)",
+            "do { netstack_lwip_diag x; } while(0)",
+        );
+        build.define(
+            "LWIP_PLATFORM_ASSERT(x)",
+            "do { netstack_lwip_assert(x, __FILE__, __LINE__); } while(0)",
+        );
+    }
+    for (name, value) in lwip_defines() {
+        build.define(name, value);
+    }
+
     if let Some(sdk_include_path) = sdk_include_path() {
         build.include(sdk_include_path);
     }
@@ -128,6 +286,7 @@ fn generate_lwip_bindings() {
 
     let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
     let os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let target = env::var("TARGET").unwrap();
     let mut builder = bindgen::Builder::default()
         .header("src/lwip/wrapper.h")
         .size_t_is_usize(false)
@@ -136,9 +295,27 @@ fn generate_lwip_bindings() {
         .clang_arg("-Wno-everything")
         .layout_tests(false)
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()));
-    if arch == "aarch64" && os == "ios" {
-        // https://github.com/rust-lang/rust-bindgen/issues/1211
-        builder = builder.clang_arg("--target=arm64-apple-ios");
+    if arch == "aarch64" {
+        if let Some((_, bindgen_target)) = apple_sdk(&os, &target) {
+            // https://github.com/rust-lang/rust-bindgen/issues/1211
+            builder = builder.clang_arg(format!("--target={}", bindgen_target));
+        }
+    }
+    if os == "windows" {
+        // Keep the generated constants in lock-step with `compile_lwip()`.
+        builder = builder
+            .clang_arg("-DWIN32")
+            .clang_arg("-D_WINSOCK_DEPRECATED_NO_WARNINGS")
+            .clang_arg("-DLWIP_TIMEVAL_PRIVATE=0")
+            .clang_arg("-DLWIP_ERRNO_STDINCLUDE");
+    }
+    // Feature-driven lwipopts defines must match `compile_lwip()` so the
+    // generated `struct stats` (and friends) reflect the enabled counters.
+    for (name, value) in lwip_defines() {
+        builder = match value {
+            Some(value) => builder.clang_arg(format!("-D{}={}", name, value)),
+            None => builder.clang_arg(format!("-D{}", name)),
+        };
     }
     if let Some(sdk_include_path) = sdk_include_path {
         builder = builder.clang_arg(format!("-I{}", sdk_include_path));
@@ -0,0 +1,28 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+extern "C" {
+    fn netstack_lwip_set_log_cb(cb: Option<unsafe extern "C" fn(*const c_char)>);
+}
+
+static mut LOG_FN: Option<fn(&str)> = None;
+
+unsafe extern "C" fn trampoline(msg: *const c_char) {
+    if let Some(f) = LOG_FN {
+        if let Ok(s) = CStr::from_ptr(msg).to_str() {
+            f(s.trim_end());
+        }
+    }
+}
+
+/// Forward lwIP's printf-style diagnostics and assertions to `f`.
+///
+/// Requires the `lwip-debug` feature (which defines `LWIP_DEBUG` and the
+/// per-module masks). Pass a `log`/`tracing` shim to surface the stack's
+/// internal tracing without recompiling.
+pub fn set_log_callback(f: fn(&str)) {
+    unsafe {
+        LOG_FN = Some(f);
+        netstack_lwip_set_log_cb(Some(trampoline));
+    }
+}
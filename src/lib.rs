@@ -1,6 +1,10 @@
+#[cfg(feature = "lwip-debug")]
+pub mod debug;
 mod lwip;
 mod output;
 mod stack;
+#[cfg(feature = "stats")]
+mod stats;
 mod tcp_listener;
 mod tcp_stream;
 mod tcp_stream_context;
@@ -10,6 +14,8 @@ mod util;
 pub(crate) static LWIP_MUTEX: spin::mutex::TicketMutex<()> = spin::mutex::TicketMutex::new(());
 
 pub use stack::NetStack;
+#[cfg(feature = "stats")]
+pub use stats::{stats, Stats};
 pub use tcp_listener::TcpListener;
 pub use tcp_stream::TcpStream;
 pub use udp::UdpSocket;
@@ -0,0 +1,51 @@
+use crate::lwip::*;
+
+/// Snapshot of the lwIP counters most useful for diagnosing a stalled tunnel:
+/// pool high-water marks, heap usage, and dropped/checksum-error packets.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// TCP PCBs currently in use / peak since boot.
+    pub tcp_pcbs: u32,
+    pub tcp_pcbs_max: u32,
+    /// UDP PCBs currently in use / peak since boot.
+    pub udp_pcbs: u32,
+    pub udp_pcbs_max: u32,
+    /// Heap (`MEM_SIZE`) bytes currently used / peak used.
+    pub mem_used: u32,
+    pub mem_max: u32,
+    /// Datagrams dropped / failing checksum at the IP layer.
+    pub ip_drop: u32,
+    pub ip_chkerr: u32,
+}
+
+/// Read a snapshot of the live lwIP counters.
+///
+/// Only available with the `stats` feature, which compiles in lwIP's counters
+/// (`stats.c` and the `*_STATS` options).
+pub fn stats() -> Stats {
+    let memp = |idx: memp_t| -> (u32, u32) {
+        let p = unsafe { lwip_stats.memp[idx as usize] };
+        if p.is_null() {
+            (0, 0)
+        } else {
+            let m = unsafe { &*p };
+            (m.used as u32, m.max as u32)
+        }
+    };
+
+    let (tcp_pcbs, tcp_pcbs_max) = memp(memp_t_MEMP_TCP_PCB);
+    let (udp_pcbs, udp_pcbs_max) = memp(memp_t_MEMP_UDP_PCB);
+
+    unsafe {
+        Stats {
+            tcp_pcbs,
+            tcp_pcbs_max,
+            udp_pcbs,
+            udp_pcbs_max,
+            mem_used: lwip_stats.mem.used as u32,
+            mem_max: lwip_stats.mem.max as u32,
+            ip_drop: lwip_stats.ip.drop as u32,
+            ip_chkerr: lwip_stats.ip.chkerr as u32,
+        }
+    }
+}
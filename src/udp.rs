@@ -0,0 +1,170 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::raw::c_void;
+use std::pin::Pin;
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::stream::Stream;
+use futures::task::{Context, Poll};
+
+use crate::lwip::*;
+use crate::util;
+use crate::{Error, LWIP_MUTEX};
+
+/// A received datagram tagged with both its source and its original
+/// destination (the latter captured via `ip_current_dest_addr()`).
+type Datagram = (Vec<u8>, SocketAddr, SocketAddr);
+
+struct UdpSocketContext {
+    tx: UnboundedSender<Datagram>,
+}
+
+/// A UDP socket on the userspace stack. Received datagrams are delivered as a
+/// `(data, src, dst)` stream so transparent proxies can recover the original
+/// destination of each packet.
+pub struct UdpSocket {
+    pcb: usize,
+    rx: UnboundedReceiver<Datagram>,
+    // Boxed so its address is stable while registered as the `udp_recv` arg;
+    // reclaimed in `Drop`.
+    ctx: *mut UdpSocketContext,
+}
+
+unsafe impl Send for UdpSocket {}
+
+extern "C" {
+    fn netstack_lwip_udp_current_dest(addr: *mut ip_addr_t, port: *mut u16_t);
+}
+
+extern "C" fn udp_recv_cb(
+    arg: *mut c_void,
+    _pcb: *mut udp_pcb,
+    p: *mut pbuf,
+    src: *const ip_addr_t,
+    port: u16_t,
+) {
+    unsafe {
+        // Capture the original destination before the pbuf is consumed; the
+        // current-dest accessors are only valid inside this callback.
+        let mut dst_addr: ip_addr_t = std::mem::zeroed();
+        let mut dst_port: u16_t = 0;
+        netstack_lwip_udp_current_dest(&mut dst_addr, &mut dst_port);
+
+        let len = (*p).tot_len as usize;
+        let mut data = vec![0u8; len];
+        pbuf_copy_partial(p, data.as_mut_ptr() as *mut c_void, len as u16_t, 0);
+        pbuf_free(p);
+
+        let src = util::to_socket_addr(src, port);
+        let dst = util::to_socket_addr(&dst_addr, dst_port);
+
+        let ctx = &*(arg as *const UdpSocketContext);
+        let _ = ctx.tx.unbounded_send((data, src, dst));
+    }
+}
+
+impl UdpSocket {
+    pub fn new() -> Box<Self> {
+        let _g = LWIP_MUTEX.lock();
+        unsafe {
+            let pcb = udp_new_ip_type(IPADDR_TYPE_ANY as u8);
+            let (tx, rx) = unbounded();
+            let ctx = Box::into_raw(Box::new(UdpSocketContext { tx }));
+            udp_recv(pcb, Some(udp_recv_cb), ctx as *mut c_void);
+            Box::new(UdpSocket {
+                pcb: pcb as usize,
+                rx,
+                ctx,
+            })
+        }
+    }
+
+    pub fn send_to(&self, data: &[u8], addr: &SocketAddr) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        unsafe {
+            let p = pbuf_alloc(pbuf_layer_PBUF_TRANSPORT, data.len() as u16_t, pbuf_type_PBUF_RAM);
+            if p.is_null() {
+                return Err(Error::LwIP(err_enum_t_ERR_MEM as i8));
+            }
+            pbuf_take(p, data.as_ptr() as *const c_void, data.len() as u16_t);
+            let dst = util::to_ip_addr(addr);
+            let err = udp_sendto(self.pcb as *mut udp_pcb, p, &dst, addr.port());
+            pbuf_free(p);
+            if err != err_enum_t_ERR_OK as i8 {
+                return Err(Error::LwIP(err));
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind this socket's egress to the stack's single netif.
+    ///
+    /// Requires the `udp-bind-netif` feature (which defines
+    /// `HAVE_LWIP_UDP_BIND_NETIF`).
+    #[cfg(feature = "udp-bind-netif")]
+    pub fn bind_to_netif(&self) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        unsafe { udp_bind_netif(self.pcb as *mut udp_pcb, netif_default) };
+        Ok(())
+    }
+
+    /// Join an IPv4 multicast group, routed through the stack's single netif.
+    pub fn join_multicast_v4(&self, group: Ipv4Addr) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        let g = util::to_ip4_addr(group);
+        let err = unsafe { igmp_joingroup_netif(netif_default, &g) };
+        lwip_result(err)
+    }
+
+    /// Leave a previously joined IPv4 multicast group.
+    pub fn leave_multicast_v4(&self, group: Ipv4Addr) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        let g = util::to_ip4_addr(group);
+        let err = unsafe { igmp_leavegroup_netif(netif_default, &g) };
+        lwip_result(err)
+    }
+
+    /// Join an IPv6 multicast group, routed through the stack's single netif.
+    pub fn join_multicast_v6(&self, group: Ipv6Addr) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        let g = util::to_ip6_addr(group);
+        let err = unsafe { mld6_joingroup_netif(netif_default, &g) };
+        lwip_result(err)
+    }
+
+    /// Leave a previously joined IPv6 multicast group.
+    pub fn leave_multicast_v6(&self, group: Ipv6Addr) -> Result<(), Error> {
+        let _g = LWIP_MUTEX.lock();
+        let g = util::to_ip6_addr(group);
+        let err = unsafe { mld6_leavegroup_netif(netif_default, &g) };
+        lwip_result(err)
+    }
+}
+
+fn lwip_result(err: err_t) -> Result<(), Error> {
+    if err == err_enum_t_ERR_OK as i8 {
+        Ok(())
+    } else {
+        Err(Error::LwIP(err))
+    }
+}
+
+impl Stream for UdpSocket {
+    /// Yields `(data, src, dst)`: the payload, the sender, and the original
+    /// destination address/port of each datagram.
+    type Item = Datagram;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        let _g = LWIP_MUTEX.lock();
+        unsafe {
+            udp_recv(self.pcb as *mut udp_pcb, None, std::ptr::null_mut());
+            udp_remove(self.pcb as *mut udp_pcb);
+            drop(Box::from_raw(self.ctx));
+        }
+    }
+}
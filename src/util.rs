@@ -0,0 +1,56 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::lwip::*;
+
+/// Build an lwIP `ip4_addr_t` (network byte order) from an `Ipv4Addr`.
+pub fn to_ip4_addr(v4: Ipv4Addr) -> ip4_addr_t {
+    ip4_addr_t {
+        addr: u32::from(v4).to_be(),
+    }
+}
+
+/// Build an lwIP `ip6_addr_t` (network byte order words) from an `Ipv6Addr`.
+pub fn to_ip6_addr(v6: Ipv6Addr) -> ip6_addr_t {
+    let o = v6.octets();
+    let mut addr = [0u32; 4];
+    for (i, word) in addr.iter_mut().enumerate() {
+        // `from_ne_bytes` preserves the network byte layout in memory.
+        *word = u32::from_ne_bytes([o[i * 4], o[i * 4 + 1], o[i * 4 + 2], o[i * 4 + 3]]);
+    }
+    ip6_addr_t { addr, zone: 0 }
+}
+
+/// Build a type-tagged `ip_addr_t` from a `SocketAddr`'s IP.
+pub fn to_ip_addr(addr: &SocketAddr) -> ip_addr_t {
+    let mut out: ip_addr_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        match addr.ip() {
+            IpAddr::V4(v4) => {
+                out.type_ = IPADDR_TYPE_V4 as u8;
+                out.u_addr.ip4 = to_ip4_addr(v4);
+            }
+            IpAddr::V6(v6) => {
+                out.type_ = IPADDR_TYPE_V6 as u8;
+                out.u_addr.ip6 = to_ip6_addr(v6);
+            }
+        }
+    }
+    out
+}
+
+/// Decode a type-tagged `ip_addr_t` plus port into a `SocketAddr`.
+///
+/// # Safety
+/// `addr` must point to a valid `ip_addr_t` for the duration of the call.
+pub unsafe fn to_socket_addr(addr: *const ip_addr_t, port: u16) -> SocketAddr {
+    let a = &*addr;
+    if a.type_ as u32 == IPADDR_TYPE_V6 {
+        let mut bytes = [0u8; 16];
+        for (i, word) in a.u_addr.ip6.addr.iter().enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&word.to_ne_bytes());
+        }
+        SocketAddr::new(IpAddr::V6(Ipv6Addr::from(bytes)), port)
+    } else {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::from(u32::from_be(a.u_addr.ip4.addr))), port)
+    }
+}